@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -8,11 +9,32 @@ pub struct Node<'a> {
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum NodeType<'a> {
-    Text(&'a str),
+    Text(Cow<'a, str>),
     Element(ElementData<'a>),
+    Comment(&'a str),
 }
 
-pub type AttrMap<'a> = HashMap<&'a str, &'a str>;
+///an attribute's value as written in the source: quoted (`name="v"` or
+///`name='v'`), unquoted (`name=v`), or a bare boolean attribute with no
+///value at all, e.g. `disabled` as opposed to `disabled=""`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Attr<'a> {
+    Boolean,
+    Quoted(Cow<'a, str>),
+    Unquoted(Cow<'a, str>),
+}
+
+impl<'a> Attr<'a> {
+    ///the attribute's value, or `None` for a boolean attribute
+    pub fn value(&self) -> Option<&Cow<'a, str>> {
+        match self {
+            Attr::Boolean => None,
+            Attr::Quoted(v) | Attr::Unquoted(v) => Some(v),
+        }
+    }
+}
+
+pub type AttrMap<'a> = HashMap<&'a str, Attr<'a>>;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct ElementData<'a> {
@@ -20,14 +42,21 @@ pub struct ElementData<'a> {
     pub attributes: AttrMap<'a>,
 }
 
-pub fn text<'a>(data: &'a str) -> Node<'a> {
-    Node { 
-        children: vec![], 
-        node_type: NodeType::Text(data)
+pub fn text<'a>(data: impl Into<Cow<'a, str>>) -> Node<'a> {
+    Node {
+        children: vec![],
+        node_type: NodeType::Text(data.into())
+    }
+}
+
+pub fn comment<'a>(data: &'a str) -> Node<'a> {
+    Node {
+        children: vec![],
+        node_type: NodeType::Comment(data)
     }
 }
 
-pub fn elem<'a>(name: &'a str, attrs: AttrMap<'a>, 
+pub fn elem<'a>(name: &'a str, attrs: AttrMap<'a>,
     children: Vec<Node<'a>>) -> Node<'a> 
 {
     Node {
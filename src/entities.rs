@@ -0,0 +1,103 @@
+use std::borrow::Cow;
+
+///decode HTML entity references (`&amp;`, `&#169;`, `&#xA9;`) in `s`.
+///Borrows `s` unchanged when no `&` is present, so plain text stays
+///zero-copy; unterminated or unknown references are emitted literally.
+pub fn decode<'a>(s: &'a str) -> Cow<'a, str> {
+    match s.find('&') {
+        None => Cow::Borrowed(s),
+        Some(_) => Cow::Owned(decode_owned(s)),
+    }
+}
+
+fn decode_owned(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        match decode_one(rest) {
+            Some((ch, consumed)) => {
+                out.push(ch);
+                rest = &rest[consumed..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
+
+///try to decode a single entity reference starting at `s[0] == '&'`,
+///returning the decoded char and how many bytes it consumed
+fn decode_one(s: &str) -> Option<(char, usize)> {
+    let semi = s.find(';')?;
+    let body = &s[1..semi];
+
+    let ch = if let Some(hex) = body.strip_prefix('#').and_then(|b| b.strip_prefix(['x', 'X'])) {
+        char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+    } else if let Some(dec) = body.strip_prefix('#') {
+        char::from_u32(dec.parse().ok()?)?
+    } else {
+        named_entity(body)?
+    };
+
+    Some((ch, semi + 1))
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_entities_borrows() {
+        let decoded = decode("plain text");
+        assert_eq!("plain text", decoded);
+        assert!(matches!(decoded, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn named_entities() {
+        assert_eq!("&", decode("&amp;"));
+        assert_eq!("<a> & \"b\" 'c'", decode("&lt;a&gt; &amp; &quot;b&quot; &apos;c&apos;"));
+    }
+
+    #[test]
+    fn numeric_entities() {
+        assert_eq!("©", decode("&#169;"));
+        assert_eq!("©", decode("&#xA9;"));
+        assert_eq!("©", decode("&#xa9;"));
+    }
+
+    #[test]
+    fn unterminated_reference_is_literal() {
+        assert_eq!("a &amp no semicolon", decode("a &amp no semicolon"));
+    }
+
+    #[test]
+    fn unknown_reference_is_literal() {
+        assert_eq!("&bogus;", decode("&bogus;"));
+    }
+
+    #[test]
+    fn entity_in_the_middle() {
+        assert_eq!("Tom & Jerry", decode("Tom &amp; Jerry"));
+    }
+}
@@ -4,21 +4,23 @@ pub use token::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LexerError<'a> {
-    UnknownLexeme(&'a str),
+    UnknownLexeme { lexeme: &'a str, span: Span },
 }
 
 pub type LexerResult<'a> = Result<Token<'a>, LexerError<'a>>;
+pub type SpannedLexerResult<'a> = Result<Spanned<Token<'a>>, LexerError<'a>>;
 
 
 ///just a token stream
 #[derive(Debug, Clone, Copy)]
 pub struct Lexer<'a> {
     source: &'a str,
+    original: &'a str,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a str) -> Self {
-        Self { source }
+        Self { source, original: source }
     }
 
     pub fn peek(&mut self) -> LexerResult<'a> {
@@ -31,15 +33,23 @@ impl<'a> Lexer<'a> {
         };
 
         match ch {
+            '<' if self.source.starts_with("<!--") => Ok(Token::Comment(self.comment_content())),
+            '<' if self.source.starts_with("<![CDATA[") => Ok(Token::CDataStart),
+            '<' if self.starts_with_doctype() => Ok(Token::Doctype(self.doctype_content())),
             '<' => match ch2 {
                 Some('/') => Ok(Token::CloseTagStart),
                 _ => Ok(Token::OpenTagStart),
             }
+            '/' if ch2 == Some('>') => Ok(Token::SelfCloseTagEnd),
             '>' => Ok(Token::TagEnd),
             '"' => Ok(Token::Quote),
+            '\'' => Ok(Token::SingleQuote),
             '=' => Ok(Token::Equals),
             ch if ch.is_alphabetic() => Ok(Token::Identifier("")),
-            _ => Err(LexerError::UnknownLexeme(self.source)),
+            _ => Err(LexerError::UnknownLexeme {
+                lexeme: self.source,
+                span: self.span_here(),
+            }),
         }
     }
 
@@ -48,10 +58,28 @@ impl<'a> Lexer<'a> {
         match t {
             EOF => t,
             Identifier(_) => Token::Identifier(self.take_identifier()),
-            CloseTagStart => {
+            CloseTagStart | SelfCloseTagEnd => {
                 self.source = &self.source[2..];
                 t
             }
+            Comment(_) => {
+                let end = self.source.find("-->")
+                    .map(|i| i + 3)
+                    .unwrap_or(self.source.len());
+                self.cut_front(end);
+                t
+            }
+            Doctype(_) => {
+                let end = self.source.find('>')
+                    .map(|i| i + 1)
+                    .unwrap_or(self.source.len());
+                self.cut_front(end);
+                t
+            }
+            CDataStart => {
+                self.source = &self.source["<![CDATA[".len()..];
+                t
+            }
             _ => {
                 self.source = &self.source[1..];
                 t
@@ -59,8 +87,27 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    pub fn next(&mut self) -> LexerResult<'a> {
-        self.peek().and_then(|t| Ok(self.take(t)))
+    ///consume a "<![CDATA[ ... ]]>" section (the `CDataStart` marker must
+    ///already have been taken) and return its raw, undecoded content
+    pub fn cdata_till(&mut self) -> &'a str {
+        let end = self.source.find("]]>").unwrap_or(self.source.len());
+        let content = self.cut_front(end);
+        if self.source.starts_with("]]>") {
+            self.source = &self.source[3..];
+        }
+        content
+    }
+
+    pub fn next(&mut self) -> SpannedLexerResult<'a> {
+        self.source = self.source.trim_start();
+        let start = self.offset();
+        let (line, col) = self.line_col(start);
+
+        let t = self.peek()?;
+        let value = self.take(t);
+        let end = self.offset();
+
+        Ok(Spanned { value, span: Span { start, end, line, col } })
     }
 
 
@@ -71,6 +118,26 @@ impl<'a> Lexer<'a> {
         self.cut_front(n).trim_end()
     }
 
+    ///peek at the next non-whitespace character without erroring on
+    ///lexemes that aren't a valid standalone token, e.g. the start of an
+    ///unquoted attribute value
+    pub fn peek_char(&mut self) -> Option<char> {
+        self.source = self.source.trim_start();
+        self.source.chars().next()
+    }
+
+    ///consume an unquoted attribute value, terminated by whitespace, '>',
+    ///or "/>" (the start of a `SelfCloseTagEnd`); a bare '/' that isn't
+    ///immediately followed by '>' is part of the value
+    pub fn unquoted_value(&mut self) -> &'a str {
+        let n = self.source.char_indices()
+            .find(|&(i, c)| c.is_whitespace() || c == '>'
+                || (c == '/' && self.source[i + 1..].starts_with('>')))
+            .map(|(i, _)| i)
+            .unwrap_or(self.source.len());
+        self.cut_front(n)
+    }
+
     ///return remainder of source
     pub fn remainder(&self) -> &'a str {
         self.source
@@ -89,6 +156,55 @@ impl<'a> Lexer<'a> {
         self.source = &self.source[n..];
         return result;
     }
+
+    ///byte offset of the current position within the original source
+    fn offset(&self) -> usize {
+        self.original.len() - self.source.len()
+    }
+
+    ///1-based (line, col) of the given byte offset, counting newlines
+    ///consumed so far
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let consumed = &self.original[..offset];
+        let line = consumed.matches('\n').count() + 1;
+        let col = match consumed.rfind('\n') {
+            Some(i) => consumed[i + 1..].chars().count() + 1,
+            None => consumed.chars().count() + 1,
+        };
+        (line, col)
+    }
+
+    ///zero-width span at the current position, used for lexer errors
+    fn span_here(&self) -> Span {
+        let start = self.offset();
+        let (line, col) = self.line_col(start);
+        Span { start, end: start, line, col }
+    }
+
+    ///content of a "<!-- ... -->" comment starting at `self.source`,
+    ///without the delimiters
+    fn comment_content(&self) -> &'a str {
+        let start = 4; //len("<!--")
+        let end = self.source[start..].find("-->")
+            .map(|i| start + i)
+            .unwrap_or(self.source.len());
+        &self.source[start..end]
+    }
+
+    ///content of a "<!DOCTYPE ...>" declaration starting at `self.source`,
+    ///between "<!DOCTYPE" and '>'
+    fn doctype_content(&self) -> &'a str {
+        let start = 9; //len("<!DOCTYPE")
+        let end = self.source[start..].find('>')
+            .map(|i| start + i)
+            .unwrap_or(self.source.len());
+        self.source[start..end].trim()
+    }
+
+    ///whether `self.source` starts with "<!DOCTYPE", case-insensitively
+    fn starts_with_doctype(&self) -> bool {
+        self.source.get(1..9).is_some_and(|s| s.eq_ignore_ascii_case("!DOCTYPE"))
+    }
 }
 
 #[cfg(test)]
@@ -96,11 +212,15 @@ mod tests {
     use super::*;
     use Token::*;
 
+    fn next_tok<'a>(lx: &mut Lexer<'a>) -> Token<'a> {
+        lx.next().unwrap().value
+    }
+
     #[test]
     fn peek_simple() {
         let tests = [
             (OpenTagStart, "<"), (CloseTagStart, "</"),
-            (TagEnd, ">"), (Quote, "\""), (Equals, "="),
+            (TagEnd, ">"), (SelfCloseTagEnd, "/>"), (Quote, "\""), (Equals, "="),
             (Identifier(""), "asdf"), (EOF, ""),
         ];
         for (t, s) in tests {
@@ -112,13 +232,13 @@ mod tests {
     fn take_simple_eof() {
         let tests = [
             (OpenTagStart, "<"), (CloseTagStart, "</"),
-            (TagEnd, ">"), (Quote, "\""), (Equals, "="),
+            (TagEnd, ">"), (SelfCloseTagEnd, "/>"), (Quote, "\""), (Equals, "="),
             (EOF, ""),
         ];
         for (t, s) in tests {
             let mut lx = Lexer::new(s);
-            assert_eq!(Ok(t), lx.next());
-            assert_eq!(Ok(EOF), lx.next());
+            assert_eq!(t, next_tok(&mut lx));
+            assert_eq!(EOF, next_tok(&mut lx));
         }
     }
 
@@ -130,8 +250,8 @@ mod tests {
         ];
         for (t, s) in tests {
             let mut lx = Lexer::new(s);
-            assert_eq!(Ok(t), lx.next());
-            assert_eq!(Ok(Token::EOF), lx.next());
+            assert_eq!(t, next_tok(&mut lx));
+            assert_eq!(Token::EOF, next_tok(&mut lx));
         }
     }
 
@@ -145,7 +265,7 @@ mod tests {
         ];
         let mut lx = Lexer::new(s);
         for t in tokens {
-            assert_eq!(Ok(t), lx.next());
+            assert_eq!(t, next_tok(&mut lx));
         }
     }
 
@@ -153,36 +273,129 @@ mod tests {
     fn text_till() {
         let mut lx = Lexer::new("asdf</");
         assert_eq!("asdf", lx.text_till('<'));
-        assert_eq!(Ok(CloseTagStart), lx.next());
-        assert_eq!(Ok(EOF), lx.next());
+        assert_eq!(CloseTagStart, next_tok(&mut lx));
+        assert_eq!(EOF, next_tok(&mut lx));
+    }
+
+    #[test]
+    fn tracks_line_and_col() {
+        let s = "<html>\n  <body>";
+        let mut lx = Lexer::new(s);
+        let html_open = lx.next().unwrap();
+        assert_eq!(html_open.span, Span { start: 0, end: 1, line: 1, col: 1 });
+
+        assert_eq!(Identifier("html"), next_tok(&mut lx));
+        assert_eq!(TagEnd, next_tok(&mut lx));
+
+        let body_open = lx.next().unwrap();
+        assert_eq!(body_open.span, Span { start: 9, end: 10, line: 2, col: 3 });
     }
 
     fn get_ident<'a>(lx: &mut Lexer<'a>) -> &'a str {
-        match lx.next() {
-            Ok(Identifier(ident)) => ident,
+        match next_tok(lx) {
+            Identifier(ident) => ident,
             other => panic!("expected identifier, got {:?}", other),
         }
     }
 
     fn tag<'a>(lx: &mut Lexer<'a>, open: bool) -> &'a str {
         let t = if open { OpenTagStart } else { CloseTagStart };
-        assert_eq!(Ok(t), lx.next());
+        assert_eq!(t, next_tok(lx));
         let ident = get_ident(lx);
-        assert_eq!(Ok(TagEnd), lx.next());
+        assert_eq!(TagEnd, next_tok(lx));
 
         ident
     }
 
     fn attrib<'a>(lx: &mut Lexer<'a>) -> (&'a str, &'a str) {
         let name = get_ident(lx);
-        assert_eq!(Ok(Equals), lx.next());
-        assert_eq!(Ok(Quote), lx.next());
+        assert_eq!(Equals, next_tok(lx));
+        assert_eq!(Quote, next_tok(lx));
         let value = lx.text_till('"');
-        assert_eq!(Ok(Quote), lx.next());
+        assert_eq!(Quote, next_tok(lx));
 
         (name, value)
     }
 
+    #[test]
+    fn comment_token() {
+        let mut lx = Lexer::new("<!-- hi there -->after");
+        assert_eq!(Comment(" hi there "), next_tok(&mut lx));
+        assert_eq!("after", lx.text_till('<'));
+    }
+
+    #[test]
+    fn unterminated_comment_runs_to_eof() {
+        let mut lx = Lexer::new("<!-- never closed");
+        assert_eq!(Comment(" never closed"), next_tok(&mut lx));
+        assert_eq!(EOF, next_tok(&mut lx));
+    }
+
+    #[test]
+    fn doctype_token() {
+        let mut lx = Lexer::new("<!DOCTYPE html>after");
+        assert_eq!(Doctype("html"), next_tok(&mut lx));
+        assert_eq!("after", lx.text_till('<'));
+    }
+
+    #[test]
+    fn doctype_token_case_insensitive() {
+        let mut lx = Lexer::new("<!doctype html>");
+        assert_eq!(Doctype("html"), next_tok(&mut lx));
+    }
+
+    #[test]
+    fn cdata_section() {
+        let mut lx = Lexer::new("<![CDATA[<b>not a tag</b>]]>after");
+        assert_eq!(CDataStart, next_tok(&mut lx));
+        assert_eq!("<b>not a tag</b>", lx.cdata_till());
+        assert_eq!("after", lx.text_till('<'));
+    }
+
+    #[test]
+    fn single_quote_token() {
+        let mut lx = Lexer::new("'value'");
+        assert_eq!(SingleQuote, next_tok(&mut lx));
+        assert_eq!("value", lx.text_till('\''));
+        assert_eq!(SingleQuote, next_tok(&mut lx));
+    }
+
+    #[test]
+    fn peek_char_ignores_leading_whitespace() {
+        let mut lx = Lexer::new("   640");
+        assert_eq!(Some('6'), lx.peek_char());
+        assert_eq!("640", lx.unquoted_value());
+        assert_eq!(None, lx.peek_char());
+    }
+
+    #[test]
+    fn unquoted_value_stops_at_whitespace_or_tag_end() {
+        let mut lx = Lexer::new("640 height=480>");
+        assert_eq!("640", lx.unquoted_value());
+        assert_eq!("height", get_ident(&mut lx));
+        assert_eq!(Equals, next_tok(&mut lx));
+        assert_eq!("480", lx.unquoted_value());
+        assert_eq!(TagEnd, next_tok(&mut lx));
+    }
+
+    #[test]
+    fn unquoted_value_stops_at_self_close() {
+        let mut lx = Lexer::new("x/>");
+        assert_eq!("x", lx.unquoted_value());
+        assert_eq!(SelfCloseTagEnd, next_tok(&mut lx));
+    }
+
+    #[test]
+    fn unquoted_value_keeps_embedded_slashes() {
+        let mut lx = Lexer::new("/index.html>");
+        assert_eq!("/index.html", lx.unquoted_value());
+        assert_eq!(TagEnd, next_tok(&mut lx));
+
+        let mut lx = Lexer::new("image/png.png />");
+        assert_eq!("image/png.png", lx.unquoted_value());
+        assert_eq!(SelfCloseTagEnd, next_tok(&mut lx));
+    }
+
     #[test]
     fn simple_html_doc() {
         let s = r#"
@@ -198,10 +411,10 @@ mod tests {
         assert_eq!("body", tag(&mut lx, true));
         assert_eq!("Hello world!", lx.text_till('<'));
 
-        assert_eq!(Ok(OpenTagStart), lx.next());
+        assert_eq!(OpenTagStart, next_tok(&mut lx));
         assert_eq!("a", get_ident(&mut lx));
         assert_eq!(("href", "google.com"), attrib(&mut lx));
-        assert_eq!(Ok(TagEnd), lx.next());
+        assert_eq!(TagEnd, next_tok(&mut lx));
         assert_eq!("google", lx.text_till('<'));
         assert_eq!("a", tag(&mut lx, false));
 
@@ -1,11 +1,32 @@
+///byte-offset and line/column position of a token within the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+///a value paired with the span of source it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Token<'a> {
     OpenTagStart, //'<'
     CloseTagStart, //"</"
     TagEnd, //'>'
+    SelfCloseTagEnd, //"/>"
     Quote, //'"'
+    SingleQuote, //'\''
     Equals, //'='
     Identifier(&'a str), //any alphanumeric word, e.g. 'cat12'
+    Comment(&'a str), //"<!-- ... -->", content between the delimiters
+    Doctype(&'a str), //"<!DOCTYPE ...>", content between "<!DOCTYPE" and '>'
+    CDataStart, //"<![CDATA["
     EOF //end of file
 }
 
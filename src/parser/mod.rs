@@ -1,7 +1,7 @@
-
 use crate::{
-    dom::*, 
+    dom::*,
     lexer::*,
+    entities,
 };
 
 #[derive(Debug, PartialEq, Eq)]
@@ -10,141 +10,409 @@ pub enum ParseError<'a> {
     UnexpectedToken {
         expected: Token<'a>,
         got: Token<'a>,
-        src: &'a str,
+        span: Span,
     },
     TagMismatch {
         opened: &'a str,
         closed: &'a str,
+        span: Span,
+    }
+}
+
+impl<'a> ParseError<'a> {
+    ///render a caret-style diagnostic, e.g. "line 3, col 5: unexpected token ..."
+    pub fn render(&self) -> String {
+        match self {
+            ParseError::LexerError(LexerError::UnknownLexeme { lexeme, span }) => format!(
+                "line {}, col {}: unknown lexeme starting at {:?}",
+                span.line, span.col, lexeme.chars().next()
+            ),
+            ParseError::UnexpectedToken { expected, got, span } => format!(
+                "line {}, col {}: unexpected token {:?}, expected {:?}",
+                span.line, span.col, got, expected
+            ),
+            ParseError::TagMismatch { opened, closed, span } => format!(
+                "line {}, col {}: mismatched closing tag </{}>, expected </{}>",
+                span.line, span.col, closed, opened
+            ),
+        }
     }
 }
 
 pub type ParseResult<'a> = Result<Node<'a>, ParseError<'a>>;
 
-pub fn parse<'a>(lx: Lexer<'a>) -> ParseResult<'a> {
-    Parser { lx }.node()
+///HTML elements that never have children and don't need a closing tag,
+///e.g. `<br>` or `<img src="x">`
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input",
+    "link", "meta", "source", "track", "wbr",
+];
+
+fn is_void_element(tag_name: &str) -> bool {
+    VOID_ELEMENTS.contains(&tag_name)
 }
 
-struct Parser<'a> {
-    lx: Lexer<'a>,
+fn next_tok<'a>(lx: &mut Lexer<'a>) -> Result<Spanned<Token<'a>>, ParseError<'a>> {
+    lx.next().map_err(ParseError::LexerError)
 }
 
-impl<'a> Parser<'a> {
-    fn nodes(&mut self) 
-        -> Result<Vec<Node<'a>>, ParseError<'a>> 
-    {
-        let mut ns = vec![];
-        loop {
-            if let Ok(Token::CloseTagStart) = self.peek() {
-                break;
-            }
-            ns.push(self.node()?);
-        }
+fn peek_tok<'a>(lx: &mut Lexer<'a>) -> Result<Token<'a>, ParseError<'a>> {
+    lx.peek().map_err(ParseError::LexerError)
+}
 
-        Ok(ns)
+fn eat_tok<'a>(lx: &mut Lexer<'a>, expected: Token<'a>) -> Result<Token<'a>, ParseError<'a>> {
+    let Spanned { value: got, span } = next_tok(lx)?;
+    if got.same_type(&expected) {
+        Ok(got)
+    } else {
+        Err(ParseError::UnexpectedToken { expected, got, span })
     }
+}
 
-    fn node(&mut self) -> ParseResult<'a> {
-        match self.peek() {
-            Ok(Token::OpenTagStart) => self.element(),
-            _ => Ok(text(self.lx.text_till('<'))) //consider unknown lexeme as text
-        }         
+fn eat_ident_tok<'a>(lx: &mut Lexer<'a>) -> Result<Spanned<&'a str>, ParseError<'a>> {
+    let Spanned { value, span } = next_tok(lx)?;
+    match value {
+        Token::Identifier(id) => Ok(Spanned { value: id, span }),
+        got => Err(ParseError::UnexpectedToken {
+            got,
+            expected: Token::Identifier(""),
+            span
+        })
     }
+}
 
-    fn element(&mut self) -> ParseResult<'a> {
-        self.eat(Token::OpenTagStart)?;
-        let tag_name = self.eat_ident()?;
-        let attrs = self.attributes()?;
-        self.eat(Token::TagEnd)?;
+fn attributes_tok<'a>(lx: &mut Lexer<'a>) -> Result<AttrMap<'a>, ParseError<'a>> {
+    let mut attrs = AttrMap::new();
 
-        let children = self.nodes()?;
+    loop {
+        match peek_tok(lx)? {
+            Token::TagEnd | Token::SelfCloseTagEnd => break,
+            _ => {}
+        }
+        let (k, v) = attribute_tok(lx)?;
+        attrs.insert(k, v);
+    }
 
-        self.eat(Token::CloseTagStart)?;
-        let close_tag_name = self.eat_ident()?;
-        self.eat(Token::TagEnd)?;
+    Ok(attrs)
+}
 
-        if tag_name != close_tag_name {
-            Err(ParseError::TagMismatch {
-                opened: tag_name,
-                closed: close_tag_name
-            })
-        } else {
-            Ok(elem(tag_name, attrs, children))
+fn attribute_tok<'a>(lx: &mut Lexer<'a>) -> Result<(&'a str, Attr<'a>), ParseError<'a>> {
+    let attr_name = eat_ident_tok(lx)?.value;
+    if let Token::Equals = peek_tok(lx)? {
+        eat_tok(lx, Token::Equals)?;
+
+        match lx.peek_char() {
+            Some('"') => {
+                eat_tok(lx, Token::Quote)?;
+                let attr_value = lx.text_till('"');
+                eat_tok(lx, Token::Quote)?;
+                Ok((attr_name, Attr::Quoted(entities::decode(attr_value))))
+            }
+            Some('\'') => {
+                eat_tok(lx, Token::SingleQuote)?;
+                let attr_value = lx.text_till('\'');
+                eat_tok(lx, Token::SingleQuote)?;
+                Ok((attr_name, Attr::Quoted(entities::decode(attr_value))))
+            }
+            _ => {
+                let attr_value = lx.unquoted_value();
+                Ok((attr_name, Attr::Unquoted(entities::decode(attr_value))))
+            }
         }
+    } else {
+        Ok((attr_name, Attr::Boolean))
     }
+}
 
-    fn attributes(&mut self) -> Result<AttrMap<'a>, ParseError<'a>> {
-        let mut attrs = AttrMap::new();
+///a single step of a streaming, SAX-style parse: callers can act on each
+///event as it's produced instead of waiting for the whole tree
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event<'a> {
+    Enter(ElementData<'a>),
+    Exit(&'a str),
+    Text(&'a str),
+    Comment(&'a str),
+    ///raw, undecoded content of a `<![CDATA[ ... ]]>` section
+    CData(&'a str),
+}
 
-        loop {
-            if let Token::TagEnd = self.peek()? {
-                break;
-            }
-            let (k, v) = self.attribute()?;
-            attrs.insert(k, v);
+pub type EventResult<'a> = Result<Event<'a>, ParseError<'a>>;
+
+///pull-parse `lx` into a stream of `Event`s without materializing a `Node` tree.
+///Mirrors the scope of `parse`: it covers exactly one top-level node (an
+///element with all its descendants, or a single run of text).
+pub fn events<'a>(lx: Lexer<'a>) -> impl Iterator<Item = EventResult<'a>> {
+    Events { lx, stack: vec![], pending_exit: None, opened_root: false, done: false }
+}
+
+struct Events<'a> {
+    lx: Lexer<'a>,
+    ///names of currently open (non-void) elements, outermost first
+    stack: Vec<&'a str>,
+    ///an `Exit` owed for an element that had no closing tag to lex
+    ///(self-closing or a void element), yielded on the following step
+    pending_exit: Option<&'a str>,
+    ///whether the root element has been entered yet; leading comments,
+    ///DOCTYPEs, or whitespace don't count as the document's top-level node
+    opened_root: bool,
+    done: bool,
+}
+
+impl<'a> Events<'a> {
+    fn element_event(&mut self) -> EventResult<'a> {
+        eat_tok(&mut self.lx, Token::OpenTagStart)?;
+        let tag_name = eat_ident_tok(&mut self.lx)?.value;
+        let attributes = attributes_tok(&mut self.lx)?;
+
+        if let Token::SelfCloseTagEnd = peek_tok(&mut self.lx)? {
+            eat_tok(&mut self.lx, Token::SelfCloseTagEnd)?;
+            self.pending_exit = Some(tag_name);
+            return Ok(Event::Enter(ElementData { tag_name, attributes }));
+        }
+
+        eat_tok(&mut self.lx, Token::TagEnd)?;
+
+        if is_void_element(tag_name) {
+            self.pending_exit = Some(tag_name);
+        } else {
+            self.stack.push(tag_name);
         }
 
-        Ok(attrs)
+        Ok(Event::Enter(ElementData { tag_name, attributes }))
     }
 
-    fn attribute(&mut self) -> Result<(&'a str, &'a str), ParseError<'a>> {
-        let attr_name = self.eat_ident()?;
-        if let Token::Equals = self.peek()? {
-            self.eat(Token::Equals)?;
+    fn close_event(&mut self) -> EventResult<'a> {
+        eat_tok(&mut self.lx, Token::CloseTagStart)?;
+        let Spanned { value: name, span } = eat_ident_tok(&mut self.lx)?;
+        eat_tok(&mut self.lx, Token::TagEnd)?;
 
-            self.eat(Token::Quote)?;
-            let attr_value = self.lx.text_till('"');
-            self.eat(Token::Quote)?;
+        match self.stack.pop() {
+            Some(opened) if opened == name => Ok(Event::Exit(name)),
+            Some(opened) => Err(ParseError::TagMismatch { opened, closed: name, span }),
+            None => Err(ParseError::TagMismatch { opened: "", closed: name, span }),
+        }
+    }
+}
 
-            Ok((attr_name, attr_value))
-        } else {
-            Ok((attr_name, ""))
+impl<'a> Iterator for Events<'a> {
+    type Item = EventResult<'a>;
+
+    fn next(&mut self) -> Option<EventResult<'a>> {
+        if self.done {
+            return None;
+        }
+
+        if let Some(name) = self.pending_exit.take() {
+            if self.stack.is_empty() {
+                self.done = true;
+            }
+            return Some(Ok(Event::Exit(name)));
+        }
+
+        loop {
+            let event = match peek_tok(&mut self.lx) {
+                Ok(Token::EOF) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(Token::OpenTagStart) => self.element_event(),
+                //a stray close tag with the stack empty still needs to be
+                //consumed (not just reported); close_event() handles both
+                //the mismatched-opener and no-opener cases via stack.pop()
+                Ok(Token::CloseTagStart) => self.close_event(),
+                Ok(Token::Doctype(_)) => match eat_tok(&mut self.lx, Token::Doctype("")) {
+                    Ok(_) => continue, //not part of the tree
+                    Err(e) => Err(e),
+                },
+                Ok(Token::Comment(content)) =>
+                    eat_tok(&mut self.lx, Token::Comment(""))
+                        .map(|_| Event::Comment(content)),
+                Ok(Token::CDataStart) =>
+                    eat_tok(&mut self.lx, Token::CDataStart)
+                        .map(|_| Event::CData(self.lx.cdata_till())),
+                _ => Ok(Event::Text(self.lx.text_till('<'))), //consider unknown lexeme as text
+            };
+
+            match &event {
+                Err(_) => self.done = true,
+                Ok(Event::Enter(_)) => self.opened_root = true,
+                //a run of text outside any element is itself the top-level node
+                Ok(Event::Text(_) | Event::CData(_)) if self.stack.is_empty() => self.done = true,
+                Ok(Event::Exit(_) | Event::Comment(_))
+                    if self.opened_root && self.stack.is_empty() => self.done = true,
+                _ => {}
+            }
+
+            return Some(event);
         }
     }
+}
 
-    fn eat(&mut self, expected: Token<'a>) -> Result<Token<'a>, ParseError<'a>> {
-        let src = self.lx.remainder();
-        let got = self.next_token()?;
-        if got.same_type(&expected) {
-            Ok(got)
-        } else {
-            Err(ParseError::UnexpectedToken { expected, got, src })
+pub fn parse<'a>(lx: Lexer<'a>) -> ParseResult<'a> {
+    let mut open: Vec<(ElementData<'a>, Vec<Node<'a>>)> = vec![];
+    let mut root = None;
+
+    for event in events(lx) {
+        let node = match event? {
+            Event::Text(s) => text(entities::decode(s)),
+            Event::CData(s) => text(s), //raw, never entity-decoded
+            Event::Comment(s) => comment(s),
+            Event::Enter(data) => {
+                open.push((data, vec![]));
+                continue;
+            }
+            Event::Exit(_) => {
+                let (data, children) = open.pop()
+                    .expect("events() only yields a balanced Exit for a pushed Enter");
+                elem(data.tag_name, data.attributes, children)
+            }
+        };
+
+        match open.last_mut() {
+            Some((_, children)) => children.push(node),
+            None => root = Some(node),
         }
     }
 
-    fn eat_ident(&mut self) -> Result<&'a str, ParseError<'a>> {
-        let src = self.lx.remainder();
-        match self.next_token()? {
-            Token::Identifier(id) => Ok(id),
-            got => Err(ParseError::UnexpectedToken {
-                got,
-                expected: Token::Identifier(""),
-                src
-            })
+    //events() yields nothing for input with no top-level content (e.g. an
+    //empty, whitespace-only, or DOCTYPE-only document); fall back to an
+    //empty text node, matching how a bare `text_till` used to handle it
+    Ok(root.unwrap_or_else(|| text("")))
+}
+
+///parse the whole of `lx` into a forest of top-level nodes, tolerating
+///unbalanced tags instead of failing outright: a `</x>` searches the open
+///stack from the top for a matching opener and implicitly closes everything
+///above it, any remaining open elements are auto-closed at EOF, and a stray
+///close tag with no matching opener is simply dropped. Never errors.
+pub fn parse_lenient<'a>(lx: Lexer<'a>) -> Vec<Node<'a>> {
+    let mut lx = lx;
+    let mut stack: Vec<(ElementData<'a>, Vec<Node<'a>>)> = vec![];
+    let mut roots: Vec<Node<'a>> = vec![];
+
+    loop {
+        match peek_tok(&mut lx) {
+            Ok(Token::OpenTagStart) => match lenient_open(&mut lx) {
+                Some((data, self_closed)) => {
+                    if self_closed || is_void_element(data.tag_name) {
+                        let node = elem(data.tag_name, data.attributes, vec![]);
+                        push_lenient(&mut stack, &mut roots, node);
+                    } else {
+                        stack.push((data, vec![]));
+                    }
+                }
+                None => break, //malformed open tag, can't make further progress
+            },
+            Ok(Token::CloseTagStart) => match lenient_close_name(&mut lx) {
+                Some(name) => close_lenient(&mut stack, &mut roots, name),
+                None => break, //malformed close tag, can't make further progress
+            },
+            Ok(Token::Doctype(_)) => match eat_tok(&mut lx, Token::Doctype("")) {
+                Ok(_) => {} //not part of the tree
+                Err(_) => break,
+            },
+            Ok(Token::Comment(content)) => match eat_tok(&mut lx, Token::Comment("")) {
+                Ok(_) => push_lenient(&mut stack, &mut roots, comment(content)),
+                Err(_) => break,
+            },
+            Ok(Token::CDataStart) => match eat_tok(&mut lx, Token::CDataStart) {
+                Ok(_) => {
+                    let s = lx.cdata_till();
+                    push_lenient(&mut stack, &mut roots, text(s)); //raw, never entity-decoded
+                }
+                Err(_) => break,
+            },
+            Ok(Token::EOF) => break,
+            _ => {
+                let s = lx.text_till('<');
+                push_lenient(&mut stack, &mut roots, text(entities::decode(s)));
+            }
         }
     }
 
-    fn next_token(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-        self.lx.next().map_err(|e| ParseError::LexerError(e))
+    while let Some((data, children)) = stack.pop() {
+        let node = elem(data.tag_name, data.attributes, children);
+        push_lenient(&mut stack, &mut roots, node);
     }
 
-    fn peek(&mut self) -> Result<Token<'a>, ParseError<'a>> {
-        self.lx.peek().map_err(|e| ParseError::LexerError(e))
+    roots
+}
+
+fn push_lenient<'a>(
+    stack: &mut Vec<(ElementData<'a>, Vec<Node<'a>>)>,
+    roots: &mut Vec<Node<'a>>,
+    node: Node<'a>,
+) {
+    match stack.last_mut() {
+        Some((_, children)) => children.push(node),
+        None => roots.push(node),
     }
 }
 
+///close every open element from the top of the stack down to (and
+///including) the first one named `name`; drops the close tag if no
+///open element matches
+fn close_lenient<'a>(
+    stack: &mut Vec<(ElementData<'a>, Vec<Node<'a>>)>,
+    roots: &mut Vec<Node<'a>>,
+    name: &'a str,
+) {
+    if !stack.iter().any(|(data, _)| data.tag_name == name) {
+        return;
+    }
+
+    while let Some((data, children)) = stack.pop() {
+        let closed_name = data.tag_name;
+        let node = elem(data.tag_name, data.attributes, children);
+        push_lenient(stack, roots, node);
+        if closed_name == name {
+            break;
+        }
+    }
+}
+
+fn lenient_open<'a>(lx: &mut Lexer<'a>) -> Option<(ElementData<'a>, bool)> {
+    eat_tok(lx, Token::OpenTagStart).ok()?;
+    let tag_name = eat_ident_tok(lx).ok()?.value;
+    let attributes = attributes_tok(lx).ok()?;
+
+    if let Ok(Token::SelfCloseTagEnd) = peek_tok(lx) {
+        eat_tok(lx, Token::SelfCloseTagEnd).ok()?;
+        return Some((ElementData { tag_name, attributes }, true));
+    }
+
+    eat_tok(lx, Token::TagEnd).ok()?;
+    Some((ElementData { tag_name, attributes }, false))
+}
+
+fn lenient_close_name<'a>(lx: &mut Lexer<'a>) -> Option<&'a str> {
+    eat_tok(lx, Token::CloseTagStart).ok()?;
+    let name = eat_ident_tok(lx).ok()?.value;
+    eat_tok(lx, Token::TagEnd).ok()?;
+    Some(name)
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::borrow::Cow;
 
     //https://stackoverflow.com/questions/27582739/how-do-i-create-a-hashmap-literal
     macro_rules! collection {
         ($($k:expr => $v:expr),* $(,)?) => {{
             use std::iter::{Iterator, IntoIterator};
-            Iterator::collect(IntoIterator::into_iter([$(($k, $v),)*]))
+            Iterator::collect(IntoIterator::into_iter([$(($k, Attr::Quoted(Cow::from($v))),)*]))
         }};
     }
 
+    #[test]
+    fn parse_empty_or_content_free_source() {
+        for src in ["", "   \n\t ", "<!DOCTYPE html>"] {
+            assert_eq!(Ok(text("")), parse(Lexer::new(src)));
+        }
+    }
+
     #[test]
     fn lonely_html_tag() {
         let expected = elem(
@@ -227,6 +495,75 @@ mod tests {
         assert_eq!(Ok(expected), parse(Lexer::new(src)));
     }
 
+    #[test]
+    fn events_for_nested_doc() {
+        let src = r#"<body>hi<p>x</p></body>"#;
+        let evs: Result<Vec<_>, _> = events(Lexer::new(src)).collect();
+        let evs = evs.unwrap();
+
+        assert_eq!(evs, vec![
+            Event::Enter(ElementData { tag_name: "body", attributes: AttrMap::new() }),
+            Event::Text("hi"),
+            Event::Enter(ElementData { tag_name: "p", attributes: AttrMap::new() }),
+            Event::Text("x"),
+            Event::Exit("p"),
+            Event::Exit("body"),
+        ]);
+    }
+
+    #[test]
+    fn events_for_void_element() {
+        let src = r#"<img src="x">"#;
+        let evs: Result<Vec<_>, _> = events(Lexer::new(src)).collect();
+        let evs = evs.unwrap();
+
+        assert_eq!(evs, vec![
+            Event::Enter(ElementData {
+                tag_name: "img",
+                attributes: collection! { "src" => "x" },
+            }),
+            Event::Exit("img"),
+        ]);
+    }
+
+    #[test]
+    fn leading_comment_does_not_swallow_the_root() {
+        let expected = elem("html", AttrMap::new(), vec![]);
+        let src = "<!-- hi --><html></html>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn events_stop_on_tag_mismatch() {
+        let src = "<body><p>x</div></body>";
+        let evs: Vec<_> = events(Lexer::new(src)).collect();
+
+        assert!(matches!(evs.last(), Some(Err(ParseError::TagMismatch { .. }))));
+    }
+
+    #[test]
+    fn events_terminate_on_stray_top_level_close_tag() {
+        for src in ["</a>", "</foo>bar"] {
+            let evs: Vec<_> = events(Lexer::new(src)).collect();
+            assert_eq!(1, evs.len());
+            assert!(matches!(
+                evs.last(),
+                Some(Err(ParseError::TagMismatch { opened: "", .. }))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_stops_on_stray_top_level_close_tag() {
+        for src in ["</a>", "</foo>bar"] {
+            assert!(matches!(
+                parse(Lexer::new(src)),
+                Err(ParseError::TagMismatch { opened: "", .. })
+            ));
+        }
+    }
+
     #[test]
     fn attrib_single() {
         let expected = elem(
@@ -258,6 +595,99 @@ mod tests {
         assert_eq!(Ok(expected), parse(Lexer::new(src)));
     }
 
+    #[test]
+    fn single_quoted_attribute() {
+        let expected = elem(
+            "tag",
+            collection! { "attrib" => "attr val" },
+            vec![]
+        );
+        let src = "<tag attrib='attr val'></tag>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn unquoted_attribute() {
+        let mut attrs = AttrMap::new();
+        attrs.insert("width", Attr::Unquoted(Cow::from("640")));
+        let expected = elem("image", attrs, vec![]);
+        let src = "<image width=640></image>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn boolean_attribute() {
+        let mut attrs = AttrMap::new();
+        attrs.insert("disabled", Attr::Boolean);
+        let expected = elem("input", attrs, vec![]);
+        let src = "<input disabled>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn boolean_attribute_distinct_from_empty_string() {
+        let mut boolean = AttrMap::new();
+        boolean.insert("checked", Attr::Boolean);
+        let mut empty = AttrMap::new();
+        empty.insert("checked", Attr::Quoted(Cow::from("")));
+
+        assert_ne!(boolean, empty);
+        assert_eq!(None, boolean["checked"].value());
+        assert_eq!(Some(&Cow::from("")), empty["checked"].value());
+    }
+
+    #[test]
+    fn tag_mismatch_reports_span() {
+        let src = "<html>\n  <body></div>\n</html>";
+        match parse(Lexer::new(src)) {
+            Err(ParseError::TagMismatch { opened, closed, span }) => {
+                assert_eq!("body", opened);
+                assert_eq!("div", closed);
+                assert_eq!(2, span.line);
+                assert_eq!("line 2, col 11: mismatched closing tag </div>, expected </body>", ParseError::TagMismatch { opened, closed, span }.render());
+            }
+            other => panic!("expected TagMismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn self_closing_tag() {
+        let expected = elem(
+            "img",
+            collection! { "src" => "x" },
+            vec![]
+        );
+        let src = r#"<img src="x" />"#;
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn void_element_without_close_tag() {
+        let expected = elem("br", AttrMap::new(), vec![]);
+        let src = "<br>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn void_element_among_siblings() {
+        let expected = elem(
+            "body",
+            AttrMap::new(),
+            vec![
+                elem("img", collection! { "src" => "x" }, vec![]),
+                text("hi"),
+            ]
+        );
+        let src = r#"<body><img src="x">hi</body>"#;
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
     #[test]
     fn nested_text() {
         let expected = elem(
@@ -320,4 +750,140 @@ mod tests {
 
         assert_eq!(Ok(expected), parse(Lexer::new(src)));
     }
+
+    #[test]
+    fn lenient_closes_remaining_elements_at_eof() {
+        let src = "<ul><li>one";
+        let expected = vec![elem(
+            "ul",
+            AttrMap::new(),
+            vec![elem("li", AttrMap::new(), vec![text("one")])]
+        )];
+
+        assert_eq!(expected, parse_lenient(Lexer::new(src)));
+    }
+
+    #[test]
+    fn lenient_unclosed_sibling_tags_nest_until_closed() {
+        let src = "<p>a<p>b";
+        let expected = vec![elem(
+            "p",
+            AttrMap::new(),
+            vec![
+                text("a"),
+                elem("p", AttrMap::new(), vec![text("b")]),
+            ]
+        )];
+
+        assert_eq!(expected, parse_lenient(Lexer::new(src)));
+    }
+
+    #[test]
+    fn lenient_close_implicitly_closes_everything_above_it() {
+        let src = "<ul><li>one<li>two</ul>";
+        let expected = vec![elem(
+            "ul",
+            AttrMap::new(),
+            vec![elem(
+                "li",
+                AttrMap::new(),
+                vec![
+                    text("one"),
+                    elem("li", AttrMap::new(), vec![text("two")]),
+                ]
+            )]
+        )];
+
+        assert_eq!(expected, parse_lenient(Lexer::new(src)));
+    }
+
+    #[test]
+    fn lenient_drops_stray_close_tag() {
+        let src = "<p>hi</div></p>";
+        let expected = vec![elem("p", AttrMap::new(), vec![text("hi")])];
+
+        assert_eq!(expected, parse_lenient(Lexer::new(src)));
+    }
+
+    #[test]
+    fn lenient_void_elements_need_no_close_tag() {
+        let src = r#"<p>before<img src="x">after</p>"#;
+        let expected = vec![elem(
+            "p",
+            AttrMap::new(),
+            vec![
+                text("before"),
+                elem("img", collection! { "src" => "x" }, vec![]),
+                text("after"),
+            ]
+        )];
+
+        assert_eq!(expected, parse_lenient(Lexer::new(src)));
+    }
+
+    #[test]
+    fn comment_attached_as_sibling_node() {
+        let expected = elem(
+            "body",
+            AttrMap::new(),
+            vec![comment(" note "), text("hi")]
+        );
+        let src = "<body><!-- note -->hi</body>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn doctype_is_silently_skipped() {
+        let expected = elem("html", AttrMap::new(), vec![]);
+        let src = "<!DOCTYPE html><html></html>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn cdata_is_exposed_as_text() {
+        let expected = elem(
+            "p",
+            AttrMap::new(),
+            vec![text("<b>raw</b>")]
+        );
+        let src = "<p><![CDATA[<b>raw</b>]]></p>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn cdata_is_not_entity_decoded() {
+        let expected = elem("p", AttrMap::new(), vec![text("a &amp; b")]);
+        let src = "<p><![CDATA[a &amp; b]]></p>";
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
+
+    #[test]
+    fn lenient_handles_comments_doctype_and_cdata() {
+        let expected = vec![
+            elem(
+                "p",
+                AttrMap::new(),
+                vec![comment(" c "), text("<b>raw</b>")]
+            ),
+        ];
+        let src = "<!DOCTYPE html><p><!-- c --><![CDATA[<b>raw</b>]]>";
+
+        assert_eq!(expected, parse_lenient(Lexer::new(src)));
+    }
+
+    #[test]
+    fn decodes_entities_in_text_and_attributes() {
+        let expected = elem(
+            "p",
+            collection! { "title" => "Tom & Jerry" },
+            vec![text("Caf\u{e9} & Co")]
+        );
+        let src = r#"<p title="Tom &amp; Jerry">Caf&#233; &amp; Co</p>"#;
+
+        assert_eq!(Ok(expected), parse(Lexer::new(src)));
+    }
 }